@@ -0,0 +1,143 @@
+use std::{
+    cell::RefCell,
+    io::Read,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use quote::ToTokens;
+use syn::parse_file;
+
+mod schema;
+
+use schema::{generate_variant_accessors, Module, RType};
+
+pub type Result<T> = std::result::Result<T, String>;
+pub type TypeName = String;
+pub type TypeRef = Rc<RefCell<RType>>;
+
+/// A single entry in the serialized [`schema::Schema`] — the
+/// generator-facing shape of one AST enum or struct.
+#[derive(Debug, serde::Serialize)]
+pub struct TypeDef {
+    pub name: TypeName,
+    #[serde(flatten)]
+    pub kind: TypeDefKind,
+    /// `#[cfg(...)]` predicates gating this type's presence, own plus
+    /// whatever it inherited from its enclosing module(s).
+    pub cfgs: Vec<String>,
+    /// `#[doc = "..."]` lines, in source order.
+    pub docs: Vec<String>,
+    /// Outer attributes other than `#[doc]`/`#[cfg]` (e.g. `#[visited_node]`),
+    /// rendered as source text.
+    pub markers: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "type")]
+pub enum TypeDefKind {
+    Enum { variants: Vec<TypeName> },
+    Struct { fields: Vec<TypeName> },
+}
+
+impl From<&RType> for Option<TypeDef> {
+    fn from(ty: &RType) -> Self {
+        match ty {
+            RType::Enum(it) => Some(TypeDef {
+                name: it.ident().to_string(),
+                kind: TypeDefKind::Enum {
+                    variants: it.all_variants().into_iter().map(|v| v.ident.to_string()).collect(),
+                },
+                cfgs: render_attrs(&it.meta.cfgs),
+                docs: it.meta.docs.clone(),
+                markers: render_attrs(&it.meta.markers),
+            }),
+            RType::Struct(it) => Some(TypeDef {
+                name: it.ident().to_string(),
+                kind: TypeDefKind::Struct {
+                    fields: it
+                        .item
+                        .fields
+                        .iter()
+                        .filter_map(|field| field.ident.as_ref().map(ToString::to_string))
+                        .collect(),
+                },
+                cfgs: render_attrs(&it.meta.cfgs),
+                docs: it.meta.docs.clone(),
+                markers: render_attrs(&it.meta.markers),
+            }),
+            RType::Use(_) | RType::Const(_) | RType::Macro(_) => None,
+        }
+    }
+}
+
+fn render_attrs(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs.iter().map(|attr| attr.to_token_stream().to_string()).collect()
+}
+
+fn main() -> Result<()> {
+    let root = std::env::args().nth(1).map(PathBuf::from).ok_or("expected path to AST root file")?;
+    let accessors_out_dir = std::env::args().nth(2).map(PathBuf::from);
+
+    let linked = Module::with_path(root).load()?.expand()?.link()?;
+
+    if let Some(out_dir) = accessors_out_dir {
+        write_variant_accessors(&linked.items, &out_dir)?;
+    }
+
+    let schema = linked.build(env!("CARGO_PKG_VERSION"))?;
+
+    println!("{}", serde_json::to_string_pretty(&schema).map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+/// Emits one `impl <Enum> { is_*()/as_*() }` accessor block per linked enum,
+/// each written to `<out_dir>/<snake_case ident>.rs`, so downstream crates
+/// can stop hand-rolling this boilerplate against the AST enums.
+fn write_variant_accessors(items: &[TypeRef], out_dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(out_dir).map_err(|e| e.to_string())?;
+    for item in items {
+        if let RType::Enum(enum_) = &*item.borrow() {
+            let tokens = generate_variant_accessors(enum_);
+            let path = out_dir.join(format!("{}.rs", schema::to_snake_case(enum_.ident())));
+            std::fs::write(path, tokens.to_string()).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::{parse_quote, Attribute, ItemEnum};
+
+    use super::*;
+    use schema::{EnumMeta, REnum};
+
+    fn enum_with_meta(meta: EnumMeta) -> RType {
+        let item: ItemEnum = parse_quote!(enum Foo { Bar(u8) });
+        RType::Enum(REnum::with_meta(item, meta))
+    }
+
+    #[test]
+    fn cfgs_reach_the_serialized_type_def() {
+        let cfg: Attribute = parse_quote!(#[cfg(feature = "foo")]);
+        let ty = enum_with_meta(EnumMeta { cfgs: vec![cfg], ..EnumMeta::default() });
+
+        let def: TypeDef = Option::<TypeDef>::from(&ty).unwrap();
+        assert_eq!(def.cfgs, vec![quote::quote!(#[cfg(feature = "foo")]).to_string()]);
+    }
+
+    #[test]
+    fn docs_and_markers_reach_the_serialized_type_def() {
+        let marker: Attribute = parse_quote!(#[visited_node]);
+        let ty = enum_with_meta(EnumMeta {
+            docs: vec!["hello".to_string()],
+            markers: vec![marker],
+            ..EnumMeta::default()
+        });
+
+        let def: TypeDef = Option::<TypeDef>::from(&ty).unwrap();
+        assert_eq!(def.docs, vec!["hello".to_string()]);
+        assert_eq!(def.markers, vec![quote::quote!(#[visited_node]).to_string()]);
+    }
+}
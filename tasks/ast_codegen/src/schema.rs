@@ -1,21 +1,30 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
 use proc_macro2::TokenStream;
-use quote::{ToTokens, TokenStreamExt};
+use quote::{format_ident, quote, ToTokens, TokenStreamExt};
 use syn::{
     braced,
     parse::{Parse, ParseBuffer},
     parse_quote,
     punctuated::Punctuated,
-    Attribute, Generics, Ident, Item, ItemConst, ItemEnum, ItemMacro, ItemStruct, ItemUse, Token,
-    Type, Variant, Visibility,
+    Attribute, Expr, ExprLit, Generics, Ident, Item, ItemConst, ItemEnum, ItemMacro, ItemMod,
+    ItemStruct, ItemUse, Lit, Token, Type, Variant, Visibility,
 };
 
 use crate::TypeName;
 
-use super::{parse_file, Itertools, PathBuf, Rc, Read, RefCell, Result, TypeDef, TypeRef};
+use super::{parse_file, PathBuf, Rc, Read, RefCell, Result, TypeDef, TypeRef};
 
 #[derive(Debug, serde::Serialize)]
 pub struct Schema {
     source: PathBuf,
+    /// Package version passed to [`Module::build`]; the `ast_codegen` binary
+    /// stamps its own `CARGO_PKG_VERSION`, so consumers can tell which
+    /// generator produced the schema without cross-referencing a commit hash.
+    version: String,
     definitions: Definitions,
 }
 
@@ -39,6 +48,12 @@ impl From<Ident> for Inherit {
 #[derive(Debug, Default, Clone)]
 pub struct EnumMeta {
     pub inherits: Vec<Inherit>,
+    /// `#[cfg(...)]` predicates gating this type, own plus inherited.
+    pub cfgs: Vec<Attribute>,
+    /// `#[doc = "..."]` lines, in source order.
+    pub docs: Vec<String>,
+    /// Outer attributes other than `#[doc]`/`#[cfg]` (e.g. `#[visited_node]`).
+    pub markers: Vec<Attribute>,
 }
 
 #[derive(Debug)]
@@ -55,6 +70,23 @@ impl REnum {
     pub fn ident(&self) -> &Ident {
         &self.item.ident
     }
+
+    /// Declared variants plus every variant pulled in via `@inherit`, once
+    /// `meta.inherits` has been resolved by [`link`]. Downstream generators
+    /// should iterate this instead of `item.variants` so inherited variants
+    /// aren't missed.
+    pub fn all_variants(&self) -> Punctuated<Variant, Token![,]> {
+        let mut variants = Punctuated::new();
+        for inherit in &self.meta.inherits {
+            if let Inherit::Linked { variants: inherited, .. } = inherit {
+                variants.extend(inherited.clone());
+            }
+        }
+        variants.extend(self.item.variants.clone());
+        // `Linked` super variants are already deduped by `link`, but diamond
+        // inheritance across two different supers can still collide here.
+        dedup_variants(variants)
+    }
 }
 
 impl From<ItemEnum> for REnum {
@@ -63,9 +95,15 @@ impl From<ItemEnum> for REnum {
     }
 }
 
-/// Placeholder for now!
 #[derive(Debug, Default, Clone)]
-pub struct StructMeta;
+pub struct StructMeta {
+    /// `#[cfg(...)]` predicates gating this type, own plus inherited.
+    pub cfgs: Vec<Attribute>,
+    /// `#[doc = "..."]` lines, in source order.
+    pub docs: Vec<String>,
+    /// Outer attributes other than `#[doc]`/`#[cfg]` (e.g. `#[visited_node]`).
+    pub markers: Vec<Attribute>,
+}
 
 #[derive(Debug)]
 pub struct RStruct {
@@ -81,7 +119,29 @@ impl RStruct {
 
 impl From<ItemStruct> for RStruct {
     fn from(item: ItemStruct) -> Self {
-        Self { item, meta: StructMeta }
+        Self { item, meta: StructMeta::default() }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MacroMeta {
+    /// `#[cfg(...)]` predicates gating this macro invocation, own plus inherited.
+    pub cfgs: Vec<Attribute>,
+    /// `#[doc = "..."]` lines, in source order.
+    pub docs: Vec<String>,
+    /// Outer attributes other than `#[doc]`/`#[cfg]`.
+    pub markers: Vec<Attribute>,
+}
+
+#[derive(Debug)]
+pub struct RMacro {
+    pub item: ItemMacro,
+    pub meta: MacroMeta,
+}
+
+impl From<ItemMacro> for RMacro {
+    fn from(item: ItemMacro) -> Self {
+        Self { item, meta: MacroMeta::default() }
     }
 }
 
@@ -92,7 +152,7 @@ pub enum RType {
 
     Use(ItemUse),
     Const(ItemConst),
-    Macro(ItemMacro),
+    Macro(RMacro),
 }
 
 impl ToTokens for RType {
@@ -103,7 +163,7 @@ impl ToTokens for RType {
 
             Self::Use(it) => it.to_tokens(tokens),
             Self::Const(it) => it.to_tokens(tokens),
-            Self::Macro(it) => it.to_tokens(tokens),
+            Self::Macro(it) => it.item.to_tokens(tokens),
         }
     }
 }
@@ -115,7 +175,7 @@ impl RType {
             RType::Struct(ty) => Some(ty.ident()),
 
             RType::Use(_) => None,
-            RType::Macro(tt) => tt.ident.as_ref(),
+            RType::Macro(it) => it.item.ident.as_ref(),
             RType::Const(tt) => Some(&tt.ident),
         }
     }
@@ -137,7 +197,7 @@ impl TryFrom<Item> for RType {
         match item {
             Item::Enum(it) => Ok(RType::Enum(it.into())),
             Item::Struct(it) => Ok(RType::Struct(it.into())),
-            Item::Macro(it) => Ok(RType::Macro(it)),
+            Item::Macro(it) => Ok(RType::Macro(it.into())),
             Item::Use(it) => Ok(RType::Use(it)),
             Item::Const(it) => Ok(RType::Const(it)),
             _ => Err(String::from("Unsupported Item!")),
@@ -154,6 +214,11 @@ pub struct Module {
     pub shebang: Option<String>,
     pub attrs: Vec<Attribute>,
     pub items: Vec<TypeRef>,
+    /// Child modules reached via `mod foo;` / `mod foo { .. }`, forming a tree
+    /// rooted at this file. `items` on the root already contains the
+    /// flattened, deduplicated item set for the whole tree; this field exists
+    /// so consumers can still walk the structure file-by-file if they need to.
+    pub submodules: Vec<Module>,
     pub loaded: bool,
 }
 
@@ -167,37 +232,34 @@ impl ToTokens for Module {
 impl Module {
     pub fn with_path(path: PathBuf) -> Self {
         let module = path.file_stem().map(|it| it.to_string_lossy().to_string()).unwrap();
-        Self { path, module, shebang: None, attrs: Vec::new(), items: Vec::new(), loaded: false }
+        Self {
+            path,
+            module,
+            shebang: None,
+            attrs: Vec::new(),
+            items: Vec::new(),
+            submodules: Vec::new(),
+            loaded: false,
+        }
     }
 
+    /// Loads this module's file and recursively follows every `mod foo;` /
+    /// `mod foo { .. }` it declares, resolving child files relative to the
+    /// declaring file (honouring `#[path = "..."]` overrides). The result is
+    /// a `Module` tree alongside a single flattened, deduplicated item set on
+    /// `self.items` covering every definition file the crate is split across.
     pub fn load(mut self) -> Result<Self> {
         assert!(!self.loaded, "can't load twice!");
 
-        let mut file = std::fs::File::open(&self.path).map_err(|e| e.to_string())?;
-        let mut content = String::new();
-        file.read_to_string(&mut content).map_err(|e| e.to_string())?;
-        let file = parse_file(content.as_str()).map_err(|e| e.to_string())?;
+        let file = parse_file(read_to_string(&self.path)?.as_str()).map_err(|e| e.to_string())?;
         self.shebang = file.shebang;
         self.attrs = file.attrs;
-        self.items = file
-            .items
-            .into_iter()
-            .filter(|it| match it {
-                // Path through these for generators, doesn't get included in the final schema.
-                Item::Use(_) | Item::Const(_) => true,
-                // These contain enums with inheritance
-                Item::Macro(m) if m.mac.path.is_ident("inherit_variants") => true,
-                // Only include types with `visited_node` since right now we don't have dedicated
-                // definition files.
-                Item::Enum(ItemEnum { attrs, .. }) | Item::Struct(ItemStruct { attrs, .. }) => {
-                    attrs.iter().any(|attr| attr.path().is_ident("visited_node"))
-                }
-                _ => false,
-            })
-            .map(TryInto::try_into)
-            .map_ok(|it| Rc::new(RefCell::new(it)))
-            // .collect::<Vec<RType>>();
-            .collect::<Result<_>>()?;
+
+        let mut seen = HashMap::new();
+        let root_cfgs = cfg_attrs(&self.attrs);
+        let (items, submodules) = load_items(&self.path, file.items, &mut seen, &root_cfgs)?;
+        self.items = items;
+        self.submodules = submodules;
         self.loaded = true;
         Ok(self)
     }
@@ -211,15 +273,246 @@ impl Module {
         Ok(self)
     }
 
-    pub fn build(self) -> Result<Schema> {
+    /// Resolves every enum's `Inherit::Unlinked` into `Inherit::Linked` by
+    /// copying in the fully-resolved variant list of whatever it inherits
+    /// from. Must run after `expand`, which is what first produces the
+    /// `Unlinked` entries.
+    pub fn link(self) -> Result<Self> {
         if !self.loaded {
             return Err(String::from(LOAD_ERROR));
         }
 
+        link(&self.items)?;
+        Ok(self)
+    }
+
+    /// Builds the final `Schema`, stamped with `version` (the generating
+    /// crate's package version) and with `definitions.types` sorted by type
+    /// name so the emitted JSON is deterministic across machines and file
+    /// orderings, and diffable once checked in.
+    pub fn build(self, version: impl Into<String>) -> Result<Schema> {
+        if !self.loaded {
+            return Err(String::from(LOAD_ERROR));
+        }
+
+        let mut items = self.items;
+        items.sort_by_key(|it| it.borrow().ident().map(Ident::to_string));
+
         let definitions = Definitions {
-            types: self.items.into_iter().filter_map(|it| (&*it.borrow()).into()).collect(),
+            types: items.into_iter().filter_map(|it| (&*it.borrow()).into()).collect(),
         };
-        Ok(Schema { source: self.path, definitions })
+        Ok(Schema { source: self.path, version: version.into(), definitions })
+    }
+}
+
+fn read_to_string(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut content = String::new();
+    file.read_to_string(&mut content).map_err(|e| e.to_string())?;
+    Ok(content)
+}
+
+/// Walks `items` (the top-level items of `path`, or the body of an inline
+/// `mod foo { .. }` declared within it), following every module declaration
+/// it finds. Returns the flattened, deduplicated items found along the way
+/// together with a `Module` per child file/inline module, mirroring the
+/// structure syn-codegen's `load_file` builds for its own definition tree.
+///
+/// `inherited_cfgs` are the `#[cfg(...)]` predicates already in effect for
+/// `path` (from its own inner attributes and every enclosing `mod` it was
+/// reached through); every type loaded here must additionally satisfy them.
+fn load_items(
+    path: &Path,
+    items: Vec<Item>,
+    seen: &mut HashMap<String, String>,
+    inherited_cfgs: &[Attribute],
+) -> Result<(Vec<TypeRef>, Vec<Module>)> {
+    let mut flattened = Vec::new();
+    let mut submodules = Vec::new();
+
+    for item in items {
+        match item {
+            Item::Mod(item_mod) => {
+                let own_mod_cfgs = cfg_attrs(&item_mod.attrs);
+                let (child_path, child_shebang, child_attrs, child_items, inner_cfgs) =
+                    if let Some((_, content)) = item_mod.content {
+                        (path.to_path_buf(), None, item_mod.attrs, content, Vec::new())
+                    } else {
+                        let child_path = resolve_mod_path(path, &item_mod)?;
+                        let child_file = parse_file(read_to_string(&child_path)?.as_str())
+                            .map_err(|e| e.to_string())?;
+                        let inner_cfgs = cfg_attrs(&child_file.attrs);
+                        (child_path, child_file.shebang, child_file.attrs, child_file.items, inner_cfgs)
+                    };
+
+                let mut child_cfgs = inherited_cfgs.to_vec();
+                child_cfgs.extend(own_mod_cfgs);
+                child_cfgs.extend(inner_cfgs);
+                let (nested, nested_mods) =
+                    load_items(&child_path, child_items, seen, &child_cfgs)?;
+                submodules.push(Module {
+                    path: child_path,
+                    module: item_mod.ident.to_string(),
+                    shebang: child_shebang,
+                    attrs: child_attrs,
+                    items: nested.clone(),
+                    submodules: nested_mods,
+                    loaded: true,
+                });
+                flattened.extend(nested);
+            }
+            other if should_include(&other) => {
+                if let Some(ident) = item_ident(&other) {
+                    let rendered = other.to_token_stream().to_string();
+                    match seen.get(&ident) {
+                        // Same name, same contents: re-reached via another `mod` path, skip it.
+                        Some(prev) if *prev == rendered => continue,
+                        // Same name, different contents: two distinct types collided.
+                        Some(_) => {
+                            return Err(format!(
+                                "`{ident}` is defined more than once with different contents"
+                            ))
+                        }
+                        None => {
+                            seen.insert(ident, rendered);
+                        }
+                    }
+                }
+                let own_attrs = item_attrs(&other).to_vec();
+                let mut cfgs = inherited_cfgs.to_vec();
+                cfgs.extend(cfg_attrs(&own_attrs));
+                let docs = doc_comments(&own_attrs);
+                let markers = marker_attrs(&own_attrs);
+
+                let mut rtype: RType = other.try_into()?;
+                match &mut rtype {
+                    RType::Enum(it) => {
+                        it.meta.cfgs = cfgs;
+                        it.meta.docs = docs;
+                        it.meta.markers = markers;
+                    }
+                    RType::Struct(it) => {
+                        it.meta.cfgs = cfgs;
+                        it.meta.docs = docs;
+                        it.meta.markers = markers;
+                    }
+                    RType::Macro(it) => {
+                        it.meta.cfgs = cfgs;
+                        it.meta.docs = docs;
+                        it.meta.markers = markers;
+                    }
+                    RType::Use(_) | RType::Const(_) => {}
+                }
+                flattened.push(Rc::new(RefCell::new(rtype)));
+            }
+            _ => {}
+        }
+    }
+
+    Ok((flattened, submodules))
+}
+
+/// The `#[cfg(...)]` attributes among `attrs`; these are the ones that gate
+/// whether a type/module is present at all, as opposed to other attributes.
+fn cfg_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("cfg")).cloned().collect()
+}
+
+fn item_attrs(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Enum(ItemEnum { attrs, .. })
+        | Item::Struct(ItemStruct { attrs, .. })
+        | Item::Macro(ItemMacro { attrs, .. }) => attrs,
+        _ => &[],
+    }
+}
+
+/// The text of every `#[doc = "..."]` attribute among `attrs`, in source
+/// order, with the leading space `///` comments get rewritten with trimmed.
+fn doc_comments(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter_map(|attr| {
+            if !attr.path().is_ident("doc") {
+                return None;
+            }
+            match &attr.meta.require_name_value().ok()?.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(doc), .. }) => {
+                    let text = doc.value();
+                    Some(text.strip_prefix(' ').unwrap_or(&text).to_string())
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Outer attributes other than `#[doc]`/`#[cfg]`, kept verbatim so a
+/// generator can recognize markers (e.g. `#[visited_node]`) without this
+/// loader having to know about every one of them up front.
+fn marker_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| !attr.path().is_ident("doc") && !attr.path().is_ident("cfg"))
+        .cloned()
+        .collect()
+}
+
+/// Resolves the source file a `mod foo;` declaration refers to, relative to
+/// the file it was declared in: an explicit `#[path = "..."]` wins, otherwise
+/// we look for `foo.rs` then `foo/mod.rs` next to the declaring file.
+fn resolve_mod_path(parent: &Path, item_mod: &ItemMod) -> Result<PathBuf> {
+    let dir = parent.parent().unwrap_or_else(|| Path::new(""));
+
+    if let Some(path) = item_mod.attrs.iter().find_map(path_attr) {
+        return Ok(dir.join(path));
+    }
+
+    let name = item_mod.ident.to_string();
+    let flat = dir.join(format!("{name}.rs"));
+    if flat.is_file() {
+        return Ok(flat);
+    }
+    let nested = dir.join(name).join("mod.rs");
+    if nested.is_file() {
+        return Ok(nested);
+    }
+    Err(format!("can't find source file for module `{}`", item_mod.ident))
+}
+
+fn path_attr(attr: &Attribute) -> Option<String> {
+    if !attr.path().is_ident("path") {
+        return None;
+    }
+    match &attr.meta.require_name_value().ok()?.value {
+        Expr::Lit(ExprLit { lit: Lit::Str(path), .. }) => Some(path.value()),
+        _ => None,
+    }
+}
+
+fn should_include(item: &Item) -> bool {
+    match item {
+        // Path through these for generators, doesn't get included in the final schema.
+        Item::Use(_) | Item::Const(_) => true,
+        // These contain enums with inheritance
+        Item::Macro(m) => m.mac.path.is_ident("inherit_variants"),
+        // Only include types with `visited_node` since right now we don't have dedicated
+        // definition files.
+        Item::Enum(ItemEnum { attrs, .. }) | Item::Struct(ItemStruct { attrs, .. }) => {
+            attrs.iter().any(|attr| attr.path().is_ident("visited_node"))
+        }
+        _ => false,
+    }
+}
+
+/// Dedup key for items that can legitimately appear more than once while
+/// walking the module tree (e.g. re-exported via more than one `mod` path).
+fn item_ident(item: &Item) -> Option<String> {
+    match item {
+        Item::Enum(ItemEnum { ident, .. }) | Item::Struct(ItemStruct { ident, .. }) => {
+            Some(ident.to_string())
+        }
+        _ => None,
     }
 }
 
@@ -227,6 +520,7 @@ pub fn expand(type_def: &TypeRef) -> Result<()> {
     let to_replace = match &*type_def.borrow() {
         RType::Macro(mac) => {
             let (enum_, inherits) = mac
+                .item
                 .mac
                 .parse_body_with(|input: &ParseBuffer| {
                     let attrs = input.call(Attribute::parse_outer)?;
@@ -271,9 +565,24 @@ pub fn expand(type_def: &TypeRef) -> Result<()> {
                     ))
                 })
                 .map_err(|e| e.to_string())?;
+            // The enum is parsed straight out of the macro body, so it never saw the
+            // `#[cfg]`/doc/marker attrs in effect for the `inherit_variants!` invocation
+            // itself (its own outer attrs, plus whatever the enclosing module(s) added) —
+            // fold those in from `mac.meta` alongside whatever's declared inside the body.
+            let mut cfgs = mac.meta.cfgs.clone();
+            cfgs.extend(cfg_attrs(&enum_.attrs));
+            let mut docs = mac.meta.docs.clone();
+            docs.extend(doc_comments(&enum_.attrs));
+            let mut markers = mac.meta.markers.clone();
+            markers.extend(marker_attrs(&enum_.attrs));
             Some(RType::Enum(REnum::with_meta(
                 enum_,
-                EnumMeta { inherits: inherits.into_iter().map(Into::into).collect() },
+                EnumMeta {
+                    inherits: inherits.into_iter().map(Into::into).collect(),
+                    cfgs,
+                    docs,
+                    markers,
+                },
             )))
         }
         _ => None,
@@ -286,8 +595,422 @@ pub fn expand(type_def: &TypeRef) -> Result<()> {
     Ok(())
 }
 
+/// Links every `Inherit::Unlinked` in `items` to its super-enum, inlining the
+/// super's fully-resolved variant list so downstream generators can treat
+/// `Inherit::Linked` enums as if they'd declared all their variants directly.
+pub fn link(items: &[TypeRef]) -> Result<()> {
+    let lookup: HashMap<String, TypeRef> = items
+        .iter()
+        .filter_map(|it| it.borrow().ident().map(|ident| (ident.to_string(), Rc::clone(it))))
+        .collect();
+
+    let mut resolved = HashMap::<String, Punctuated<Variant, Token![,]>>::new();
+    let mut in_progress = Vec::new();
+    for (ident, it) in &lookup {
+        if matches!(&*it.borrow(), RType::Enum(_)) {
+            resolve_variants(ident, &lookup, &mut resolved, &mut in_progress)?;
+        }
+    }
+
+    for item in items {
+        let mut item = item.borrow_mut();
+        if let RType::Enum(enum_) = &mut *item {
+            for inherit in &mut enum_.meta.inherits {
+                if let Inherit::Unlinked(super_) = inherit {
+                    let variants = resolved
+                        .get(super_)
+                        .cloned()
+                        .ok_or_else(|| format!("unknown super-enum `{super_}`"))?;
+                    *inherit = Inherit::Linked { super_: super_.clone(), variants };
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolves (and memoizes) the complete variant set of the enum named
+/// `ident` — its own declared variants plus everything pulled in
+/// transitively via `@inherit` — via a DFS over the inheritance graph. `
+/// in_progress` tracks the current DFS path so a cycle can be reported with
+/// the chain of enum names that caused it, instead of overflowing the stack.
+fn resolve_variants(
+    ident: &str,
+    lookup: &HashMap<String, TypeRef>,
+    resolved: &mut HashMap<String, Punctuated<Variant, Token![,]>>,
+    in_progress: &mut Vec<String>,
+) -> Result<Punctuated<Variant, Token![,]>> {
+    if let Some(variants) = resolved.get(ident) {
+        return Ok(variants.clone());
+    }
+    if in_progress.iter().any(|it| it == ident) {
+        in_progress.push(ident.to_string());
+        return Err(format!(
+            "cycle detected while resolving enum inheritance: {}",
+            in_progress.join(" -> ")
+        ));
+    }
+
+    let type_ref = lookup.get(ident).ok_or_else(|| format!("unknown super-enum `{ident}`"))?;
+    let (own_variants, inherits) = match &*type_ref.borrow() {
+        RType::Enum(enum_) => (enum_.item.variants.clone(), enum_.meta.inherits.clone()),
+        _ => return Err(format!("`{ident}` is not an enum")),
+    };
+
+    in_progress.push(ident.to_string());
+    let mut variants = Punctuated::new();
+    for inherit in &inherits {
+        let super_ = match inherit {
+            Inherit::Unlinked(name) => name.clone(),
+            Inherit::Linked { super_, .. } => super_.clone(),
+        };
+        variants.extend(resolve_variants(&super_, lookup, resolved, in_progress)?);
+    }
+    variants.extend(own_variants);
+    in_progress.pop();
+
+    // Two branches of a diamond (`D` inheriting both `B` and `C`, which both
+    // inherit `A`) each bring in their own already-resolved copy of `A`'s
+    // variants, so the concatenation above can contain duplicates even though
+    // no single branch does. Dedup once here so every memoized entry — and
+    // everything built from it further up the graph — is clean.
+    let variants = dedup_variants(variants);
+
+    resolved.insert(ident.to_string(), variants.clone());
+    Ok(variants)
+}
+
+/// Drops variants whose identifier has already been seen, keeping the first
+/// occurrence. Used to collapse the duplicates diamond inheritance produces.
+fn dedup_variants(variants: Punctuated<Variant, Token![,]>) -> Punctuated<Variant, Token![,]> {
+    let mut seen = HashSet::new();
+    variants.into_iter().filter(|variant| seen.insert(variant.ident.clone())).collect()
+}
+
+/// Generates `is_<variant>()` / `as_<variant>()` accessors for every variant
+/// of `enum_`, akin to derive_more's `is_variant`. Reads from
+/// [`REnum::all_variants`] rather than `enum_.item.variants` directly, so
+/// variants pulled in transitively via `@inherit` get accessors too, not just
+/// the ones the enum syntactically declares.
+pub fn generate_variant_accessors(enum_: &REnum) -> TokenStream {
+    let ty = enum_.ident();
+    let (impl_generics, ty_generics, where_clause) = enum_.item.generics.split_for_impl();
+
+    let methods = enum_.all_variants().into_iter().map(|variant| {
+        let variant_ident = &variant.ident;
+        let snake = to_snake_case(variant_ident);
+        let pattern = match &variant.fields {
+            syn::Fields::Named(_) => quote!(Self::#variant_ident { .. }),
+            syn::Fields::Unnamed(_) => quote!(Self::#variant_ident(..)),
+            syn::Fields::Unit => quote!(Self::#variant_ident),
+        };
+
+        let is_name = format_ident!("is_{snake}");
+        let is_method = quote! {
+            #[inline]
+            pub const fn #is_name(&self) -> bool {
+                matches!(self, #pattern)
+            }
+        };
+
+        let as_method = match &variant.fields {
+            syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let field_ty = &fields.unnamed.first().unwrap().ty;
+                let as_name = format_ident!("as_{snake}");
+                Some(quote! {
+                    #[inline]
+                    pub fn #as_name(&self) -> Option<&#field_ty> {
+                        match self {
+                            Self::#variant_ident(it) => Some(it),
+                            _ => None,
+                        }
+                    }
+                })
+            }
+            _ => None,
+        };
+
+        quote! {
+            #is_method
+            #as_method
+        }
+    });
+
+    quote! {
+        impl #impl_generics #ty #ty_generics #where_clause {
+            #(#methods)*
+        }
+    }
+}
+
+/// Converts a `CamelCase` identifier into `snake_case`, treating a run of
+/// uppercase letters followed by a lowercase one as "last uppercase starts a
+/// new word" so acronyms like `JSXElement` become `jsx_element` rather than
+/// `j_s_x_element`.
+pub(crate) fn to_snake_case(ident: &Ident) -> String {
+    let chars: Vec<char> = ident.to_string().chars().collect();
+    let mut out = String::new();
+    for (i, &ch) in chars.iter().enumerate() {
+        if ch.is_uppercase() {
+            let prev_lower = i > 0 && chars[i - 1].is_lowercase();
+            let prev_upper = i > 0 && chars[i - 1].is_uppercase();
+            let next_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+            if i > 0 && (prev_lower || (prev_upper && next_lower)) {
+                out.push('_');
+            }
+            out.extend(ch.to_lowercase());
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
 impl From<PathBuf> for Module {
     fn from(path: PathBuf) -> Self {
         Self::with_path(path)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn enum_with_inherits(item: ItemEnum, inherits: &[&str]) -> TypeRef {
+        let meta = EnumMeta {
+            inherits: inherits
+                .iter()
+                .map(|name| Inherit::from(syn::parse_str::<Ident>(name).unwrap()))
+                .collect(),
+            ..EnumMeta::default()
+        };
+        Rc::new(RefCell::new(RType::Enum(REnum::with_meta(item, meta))))
+    }
+
+    fn variant_names(enum_: &REnum) -> Vec<String> {
+        enum_.all_variants().into_iter().map(|variant| variant.ident.to_string()).collect()
+    }
+
+    #[test]
+    fn link_resolves_transitive_inheritance() {
+        let a: ItemEnum = parse_quote!(enum A { X(u8) });
+        let b: ItemEnum = parse_quote!(enum B { Y(u8) });
+
+        let items = vec![enum_with_inherits(a, &[]), enum_with_inherits(b, &["A"])];
+        link(&items).unwrap();
+
+        let RType::Enum(b) = &*items[1].borrow() else { unreachable!() };
+        assert_eq!(variant_names(b), vec!["X", "Y"]);
+    }
+
+    #[test]
+    fn link_dedupes_diamond_inheritance() {
+        // D inherits both B and C, which both inherit A — A's variants must
+        // only show up once in D's resolved set.
+        let a: ItemEnum = parse_quote!(enum A { X(u8) });
+        let b: ItemEnum = parse_quote!(enum B { Y(u8) });
+        let c: ItemEnum = parse_quote!(enum C { Z(u8) });
+        let d: ItemEnum = parse_quote!(enum D { W(u8) });
+
+        let items = vec![
+            enum_with_inherits(a, &[]),
+            enum_with_inherits(b, &["A"]),
+            enum_with_inherits(c, &["A"]),
+            enum_with_inherits(d, &["B", "C"]),
+        ];
+        link(&items).unwrap();
+
+        let RType::Enum(d) = &*items[3].borrow() else { unreachable!() };
+        assert_eq!(variant_names(d), vec!["X", "Y", "Z", "W"]);
+    }
+
+    #[test]
+    fn link_reports_a_cycle_instead_of_overflowing() {
+        let a: ItemEnum = parse_quote!(enum A { X(u8) });
+        let b: ItemEnum = parse_quote!(enum B { Y(u8) });
+
+        let items = vec![enum_with_inherits(a, &["B"]), enum_with_inherits(b, &["A"])];
+
+        let err = link(&items).unwrap_err();
+        assert!(err.contains("cycle"), "expected a cycle error, got: {err}");
+    }
+
+    #[test]
+    fn to_snake_case_handles_plain_words() {
+        assert_eq!(to_snake_case(&parse_quote!(Foo)), "foo");
+        assert_eq!(to_snake_case(&parse_quote!(FooBar)), "foo_bar");
+    }
+
+    #[test]
+    fn to_snake_case_keeps_acronyms_together() {
+        // A run of uppercase letters is one word up until the last letter
+        // before a lowercase one, which starts the next word.
+        assert_eq!(to_snake_case(&parse_quote!(JSXElement)), "jsx_element");
+        assert_eq!(to_snake_case(&parse_quote!(HTMLParser)), "html_parser");
+    }
+
+    #[test]
+    fn generate_variant_accessors_emits_is_and_as_methods() {
+        let item: ItemEnum = parse_quote! {
+            enum Foo {
+                Bar(u8),
+                BazQux,
+            }
+        };
+        let tokens = generate_variant_accessors(&REnum::from(item)).to_string();
+
+        let expected = quote! {
+            impl Foo {
+                #[inline]
+                pub const fn is_bar(&self) -> bool {
+                    matches!(self, Self::Bar(..))
+                }
+                #[inline]
+                pub fn as_bar(&self) -> Option<&u8> {
+                    match self {
+                        Self::Bar(it) => Some(it),
+                        _ => None,
+                    }
+                }
+                #[inline]
+                pub const fn is_baz_qux(&self) -> bool {
+                    matches!(self, Self::BazQux)
+                }
+            }
+        }
+        .to_string();
+
+        assert_eq!(tokens, expected);
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ast_codegen_test_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn resolve_mod_path_prefers_flat_file_over_mod_rs() {
+        let dir = temp_dir("flat_precedence");
+        std::fs::write(dir.join("foo.rs"), "").unwrap();
+        std::fs::create_dir_all(dir.join("foo")).unwrap();
+        std::fs::write(dir.join("foo").join("mod.rs"), "").unwrap();
+
+        let item_mod: ItemMod = parse_quote!(mod foo;);
+        let resolved = resolve_mod_path(&dir.join("lib.rs"), &item_mod).unwrap();
+        assert_eq!(resolved, dir.join("foo.rs"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resolve_mod_path_falls_back_to_mod_rs_when_no_flat_file_exists() {
+        let dir = temp_dir("mod_rs_fallback");
+        std::fs::create_dir_all(dir.join("foo")).unwrap();
+        std::fs::write(dir.join("foo").join("mod.rs"), "").unwrap();
+
+        let item_mod: ItemMod = parse_quote!(mod foo;);
+        let resolved = resolve_mod_path(&dir.join("lib.rs"), &item_mod).unwrap();
+        assert_eq!(resolved, dir.join("foo").join("mod.rs"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resolve_mod_path_honors_path_attribute() {
+        let dir = temp_dir("path_attr");
+        std::fs::write(dir.join("elsewhere.rs"), "").unwrap();
+
+        let item_mod: ItemMod = parse_quote!(#[path = "elsewhere.rs"] mod foo;);
+        let resolved = resolve_mod_path(&dir.join("lib.rs"), &item_mod).unwrap();
+        assert_eq!(resolved, dir.join("elsewhere.rs"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_items_concatenates_cfgs_through_nested_modules() {
+        let file: syn::File = parse_quote! {
+            #![cfg(feature = "root")]
+
+            #[cfg(feature = "outer")]
+            mod outer {
+                #[cfg(feature = "inner")]
+                #[visited_node]
+                struct Foo {
+                    x: u8,
+                }
+            }
+        };
+
+        let mut seen = HashMap::new();
+        let root_cfgs = cfg_attrs(&file.attrs);
+        let (items, _) = load_items(Path::new("lib.rs"), file.items, &mut seen, &root_cfgs).unwrap();
+
+        let RType::Struct(foo) = &*items[0].borrow() else { unreachable!() };
+        assert_eq!(foo.meta.cfgs.len(), 3, "expected root + outer-mod + own cfgs to all carry through");
+    }
+
+    #[test]
+    fn load_items_allows_identical_reexport_but_errors_on_conflicting_definition() {
+        let same_twice: syn::File = parse_quote! {
+            #[visited_node]
+            struct Foo {
+                x: u8,
+            }
+            mod a {
+                #[visited_node]
+                struct Foo {
+                    x: u8,
+                }
+            }
+        };
+        let mut seen = HashMap::new();
+        let (items, _) = load_items(Path::new("lib.rs"), same_twice.items, &mut seen, &[]).unwrap();
+        assert_eq!(items.len(), 1);
+
+        let conflicting: syn::File = parse_quote! {
+            #[visited_node]
+            struct Foo {
+                x: u8,
+            }
+            mod a {
+                #[visited_node]
+                struct Foo {
+                    y: u16,
+                }
+            }
+        };
+        let mut seen = HashMap::new();
+        let err = load_items(Path::new("lib.rs"), conflicting.items, &mut seen, &[]).unwrap_err();
+        assert!(err.contains("Foo"), "expected a conflict error mentioning `Foo`, got: {err}");
+    }
+
+    #[test]
+    fn build_sorts_definitions_by_name_and_stamps_the_version() {
+        let zeta: ItemStruct = parse_quote!(struct Zeta { x: u8 });
+        let alpha: ItemStruct = parse_quote!(struct Alpha { x: u8 });
+        let module = Module {
+            path: PathBuf::from("lib.rs"),
+            module: "lib".to_string(),
+            shebang: None,
+            attrs: Vec::new(),
+            items: vec![
+                Rc::new(RefCell::new(RType::Struct(zeta.into()))),
+                Rc::new(RefCell::new(RType::Struct(alpha.into()))),
+            ],
+            submodules: Vec::new(),
+            loaded: true,
+        };
+
+        let schema = module.build("1.2.3").unwrap();
+        assert_eq!(schema.version, "1.2.3");
+        assert_eq!(
+            schema.definitions.types.iter().map(|t| t.name.clone()).collect::<Vec<_>>(),
+            vec!["Alpha", "Zeta"]
+        );
+    }
 }
\ No newline at end of file